@@ -0,0 +1,160 @@
+/*
+    crypto.rs
+    ----------------------------------------------------------------------------
+    Provides end-to-end encryption for peer connections.
+
+    Features:
+      - X25519 Diffie-Hellman key exchange performed once per connection.
+      - AES-256-GCM authenticated encryption of every Message sent afterwards.
+      - Helpers to send/receive an encrypted Message as a length-prefixed,
+        MessagePack-encoded frame (see `framing.rs`).
+
+    Developer Notes:
+      - Keypairs are ephemeral: a fresh keypair is generated for every
+        connection, so compromising one session's key does not expose past
+        or future sessions.
+      - Nonces are random and unique per message; reusing a (key, nonce) pair
+        with AES-GCM breaks confidentiality, so never cache or replay one.
+    ----------------------------------------------------------------------------
+*/
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::framing::{self, DEFAULT_MAX_FRAME_SIZE};
+use crate::protocol::Message;
+
+const NONCE_LEN: usize = 12;
+
+/// Raised when the handshake bytes are malformed or a frame fails to decrypt.
+#[derive(Debug)]
+pub struct CryptoError(String);
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "crypto error: {}", self.0)
+    }
+}
+
+impl Error for CryptoError {}
+
+/// An authenticated, encrypted channel bound to a single TCP connection.
+/// Built from the X25519 shared secret negotiated at connection setup.
+pub struct SecureChannel {
+    cipher: Aes256Gcm,
+}
+
+impl SecureChannel {
+    /// Responder side of the handshake: read the peer's public key first,
+    /// then send ours, and derive the shared AES-256-GCM key.
+    pub async fn accept<S>(stream: &mut S) -> Result<Self, Box<dyn Error + Send + Sync>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+
+        let mut their_bytes = [0u8; 32];
+        stream
+            .read_exact(&mut their_bytes)
+            .await
+            .map_err(|e| CryptoError(format!("malformed handshake: {}", e)))?;
+        stream.write_all(public.as_bytes()).await?;
+
+        Self::from_shared_secret(secret, their_bytes)
+    }
+
+    /// Initiator side of the handshake: send our public key first, then
+    /// read the peer's, and derive the shared AES-256-GCM key.
+    pub async fn connect<S>(stream: &mut S) -> Result<Self, Box<dyn Error + Send + Sync>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+
+        stream.write_all(public.as_bytes()).await?;
+        let mut their_bytes = [0u8; 32];
+        stream
+            .read_exact(&mut their_bytes)
+            .await
+            .map_err(|e| CryptoError(format!("malformed handshake: {}", e)))?;
+
+        Self::from_shared_secret(secret, their_bytes)
+    }
+
+    fn from_shared_secret(secret: EphemeralSecret, their_bytes: [u8; 32]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let their_public = PublicKey::from(their_bytes);
+        let shared = secret.diffie_hellman(&their_public);
+
+        let mut hasher = Sha256::new();
+        hasher.update(shared.as_bytes());
+        let key_bytes = hasher.finalize();
+
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes));
+        Ok(SecureChannel { cipher })
+    }
+
+    /// Encrypts `plaintext` under a fresh random nonce, returning `nonce || ciphertext`.
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::from_slice(&nonce_bytes);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(nonce, plaintext)
+            .map_err(|_| CryptoError("encryption failed".to_string()))?;
+
+        let mut framed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        framed.extend_from_slice(&nonce_bytes);
+        framed.extend_from_slice(&ciphertext);
+        Ok(framed)
+    }
+
+    /// Splits off the leading nonce and decrypts the remainder. Fails closed
+    /// if the frame is too short or the GCM authentication tag doesn't verify.
+    fn decrypt(&self, framed: &[u8]) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        if framed.len() < NONCE_LEN {
+            return Err(Box::new(CryptoError("frame shorter than nonce".to_string())));
+        }
+        let (nonce_bytes, ciphertext) = framed.split_at(NONCE_LEN);
+        let nonce = Nonce::from_slice(nonce_bytes);
+        self.cipher
+            .decrypt(nonce, ciphertext)
+            .map_err(|_| Box::new(CryptoError("decryption failed (bad auth tag)".to_string())) as Box<dyn Error + Send + Sync>)
+    }
+}
+
+/// Serializes `msg` to MessagePack, encrypts it, and writes it as a single
+/// length-prefixed frame.
+pub async fn send_message<W>(writer: &mut W, channel: &SecureChannel, msg: &Message) -> Result<(), Box<dyn Error + Send + Sync>>
+where
+    W: AsyncWrite + Unpin,
+{
+    let plaintext = rmp_serde::to_vec(msg)?;
+    let framed = channel.encrypt(&plaintext)?;
+    framing::write_frame(writer, &framed).await?;
+    Ok(())
+}
+
+/// Reads a single length-prefixed frame, decrypts it, and deserializes the
+/// resulting MessagePack plaintext into a `Message`. Returns `Ok(None)` if
+/// the peer closed the connection cleanly before sending another message.
+pub async fn recv_message<R>(reader: &mut R, channel: &SecureChannel) -> Result<Option<Message>, Box<dyn Error + Send + Sync>>
+where
+    R: AsyncRead + Unpin,
+{
+    let framed = match framing::read_frame(reader, DEFAULT_MAX_FRAME_SIZE).await? {
+        Some(framed) => framed,
+        None => return Ok(None),
+    };
+    let plaintext = channel.decrypt(&framed)?;
+    Ok(Some(rmp_serde::from_slice(&plaintext)?))
+}
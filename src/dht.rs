@@ -1,60 +1,367 @@
 /*
     dht.rs
     ----------------------------------------------------------------------------
-    Implements a simple Distributed Hash Table (DHT) for the P2P file sharing system.
-    
+    Implements a Kademlia-style Distributed Hash Table for the P2P file
+    sharing system.
+
     Features:
-      - Uses an in-memory HashMap protected by a Tokio Mutex for asynchronous access.
-      - Provides methods to insert and lookup key-value pairs.
-    
+      - Each node is identified by a 256-bit ID (SHA-256 of its listen address).
+      - Distance between two IDs is their XOR, interpreted as a big integer.
+      - A routing table of 256 k-buckets (k = 20) tracks known peers, ordered
+        by last-seen so the least-recently-seen entry is evicted first.
+      - Values are still kept in a local HashMap; `network.rs` is responsible
+        for actually placing them at the k closest nodes over the wire.
+
     Developer Notes:
-      - The DHTEntry struct is defined as a placeholder for potential future metadata.
-      - GLOBAL_DHT is declared as a global instance via lazy_static.
-      - Debug statements (commented out) are available for deeper inspection during debugging.
+      - `GLOBAL_DHT` is declared as a global instance via lazy_static, as
+        before. Its self ID defaults to all-zero bytes until `set_self_id`
+        is called once the node knows its own listen address.
+      - Bucket eviction only happens after a `Ping` to the oldest entry goes
+        unanswered; callers are expected to verify liveness before evicting
+        (see `KBucket::evict_stale`).
     ----------------------------------------------------------------------------
 */
 
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::time::Instant;
 use tokio::sync::Mutex;
 use lazy_static::lazy_static;
 
-/// Represents an entry in the DHT.
-/// Currently serves as a placeholder for future extensions.
+/// Number of bits in a node ID (and therefore the number of k-buckets).
+pub const ID_BITS: usize = 256;
+
+/// Maximum number of peers held in a single k-bucket.
+pub const K: usize = 20;
+
+/// Degree of parallelism used by iterative lookups.
+pub const ALPHA: usize = 3;
+
+/// A 256-bit node identifier.
+pub type NodeId = [u8; 32];
+
+/// Hashes arbitrary bytes down to a 256-bit ID.
+pub fn sha256_id(data: &[u8]) -> NodeId {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    let digest = hasher.finalize();
+    let mut id = [0u8; 32];
+    id.copy_from_slice(&digest);
+    id
+}
+
+/// Derives a node's 256-bit ID by hashing its listen address.
+pub fn id_from_addr(addr: &str) -> NodeId {
+    sha256_id(addr.as_bytes())
+}
+
+/// Derives the 256-bit target ID a key maps to (`SHA-256(key)`), i.e. the
+/// location `store`/`FindValue` should converge on.
+pub fn id_from_key(key: &str) -> NodeId {
+    sha256_id(key.as_bytes())
+}
+
+/// XOR distance between two node IDs, treated as a 256-bit big integer.
+pub fn distance(a: &NodeId, b: &NodeId) -> NodeId {
+    let mut d = [0u8; 32];
+    for i in 0..32 {
+        d[i] = a[i] ^ b[i];
+    }
+    d
+}
+
+/// Index (0..256) of the k-bucket that should hold a peer at the given
+/// distance from us: bucket *i* holds peers sharing a `255 - i`-bit prefix.
+/// Returns `None` for a zero distance (i.e. the ID is our own).
+fn bucket_index(dist: &NodeId) -> Option<usize> {
+    let mut shared_prefix_bits = 0usize;
+    for byte in dist.iter() {
+        if *byte == 0 {
+            shared_prefix_bits += 8;
+        } else {
+            shared_prefix_bits += byte.leading_zeros() as usize;
+            break;
+        }
+    }
+    if shared_prefix_bits >= ID_BITS {
+        None
+    } else {
+        Some(ID_BITS - 1 - shared_prefix_bits)
+    }
+}
+
+/// A known peer and when we last heard from it.
 #[derive(Debug, Clone)]
-pub struct DHTEntry {
-    pub key: String,
-    pub value: String,
+pub struct Peer {
+    pub id: NodeId,
+    pub addr: String,
+    /// Recorded for callers (e.g. future staleness diagnostics); eviction
+    /// order itself is tracked by bucket position, not by comparing this.
+    #[allow(dead_code)]
+    pub last_seen: Instant,
+}
+
+/// A single k-bucket: at most `K` peers, ordered oldest-seen first so the
+/// front of the list is always the next eviction candidate.
+#[derive(Default)]
+struct KBucket {
+    peers: Vec<Peer>,
 }
 
-/// The DHT structure encapsulating a HashMap in a Mutex for concurrent access.
+impl KBucket {
+    /// Records contact with a peer: refreshes it to most-recently-seen if
+    /// already present, otherwise appends it (if there is room). Returns the
+    /// stalest peer that should be pinged before a new peer can be admitted,
+    /// if the bucket is full and the peer is not already known.
+    fn observe(&mut self, peer: Peer) -> Option<Peer> {
+        if let Some(pos) = self.peers.iter().position(|p| p.id == peer.id) {
+            self.peers.remove(pos);
+            self.peers.push(peer);
+            return None;
+        }
+        if self.peers.len() < K {
+            self.peers.push(peer);
+            None
+        } else {
+            Some(self.peers[0].clone())
+        }
+    }
+
+    /// Evicts the least-recently-seen peer (called after it fails a liveness
+    /// `Ping`) and admits the waiting replacement.
+    fn evict_stale(&mut self, replacement: Peer) {
+        if !self.peers.is_empty() {
+            self.peers.remove(0);
+        }
+        self.peers.push(replacement);
+    }
+
+    /// Removes `id` from this bucket, if present (called once the failure
+    /// detector in `membership.rs` has declared the peer `Dead`, independent
+    /// of whether its bucket happens to be full).
+    fn remove(&mut self, id: &NodeId) {
+        self.peers.retain(|p| &p.id != id);
+    }
+}
+
+/// The Kademlia routing table: 256 k-buckets keyed by distance from `self_id`.
+pub struct RoutingTable {
+    pub self_id: NodeId,
+    buckets: Vec<KBucket>,
+}
+
+impl RoutingTable {
+    fn new(self_id: NodeId) -> Self {
+        let mut buckets = Vec::with_capacity(ID_BITS);
+        for _ in 0..ID_BITS {
+            buckets.push(KBucket::default());
+        }
+        RoutingTable { self_id, buckets }
+    }
+
+    /// Records contact with a peer. If its bucket is full, returns the
+    /// stalest peer in that bucket so the caller can `Ping` it; if that ping
+    /// fails, call `evict_stale` to make room for `peer`.
+    pub fn observe(&mut self, id: NodeId, addr: String) -> Option<Peer> {
+        let peer = Peer { id, addr, last_seen: Instant::now() };
+        match bucket_index(&distance(&self.self_id, &id)) {
+            Some(idx) => self.buckets[idx].observe(peer),
+            None => None, // This is our own ID; nothing to route to.
+        }
+    }
+
+    /// Evicts the stalest peer in `replacement`'s bucket and admits it.
+    pub fn evict_stale(&mut self, replacement: Peer) {
+        if let Some(idx) = bucket_index(&distance(&self.self_id, &replacement.id)) {
+            self.buckets[idx].evict_stale(replacement);
+        }
+    }
+
+    /// Removes `id` from whichever bucket it lives in, if any. Used by the
+    /// membership failure detector (`heartbeat_loop` in `network.rs`) to
+    /// evict a peer as soon as it's declared `Dead`, instead of waiting for
+    /// its bucket to fill up before a stale entry is even considered.
+    pub fn remove(&mut self, id: &NodeId) {
+        if let Some(idx) = bucket_index(&distance(&self.self_id, id)) {
+            self.buckets[idx].remove(id);
+        }
+    }
+
+    /// Returns up to `count` peers closest to `target`, sorted nearest-first.
+    pub fn closest(&self, target: &NodeId, count: usize) -> Vec<Peer> {
+        let mut all: Vec<Peer> = self.buckets.iter().flat_map(|b| b.peers.clone()).collect();
+        all.sort_by_key(|p| distance(&p.id, target));
+        all.truncate(count);
+        all
+    }
+}
+
+/// The DHT structure combining local value storage with Kademlia routing state.
+#[allow(clippy::upper_case_acronyms)]
 pub struct DHT {
     pub store: Mutex<HashMap<String, String>>,
+    pub routing_table: Mutex<RoutingTable>,
 }
 
 impl DHT {
-    /// Creates a new, empty DHT.
+    /// Creates a new, empty DHT. The self ID defaults to zero until
+    /// `set_self_id` is called with the node's real listen address.
     pub fn new() -> Self {
         DHT {
             store: Mutex::new(HashMap::new()),
+            routing_table: Mutex::new(RoutingTable::new([0u8; 32])),
         }
     }
 
-    /// Asynchronously inserts a key-value pair into the DHT.
+    /// Sets this node's own ID, re-keying the routing table around it.
+    /// Should be called once, as soon as the node knows its listen address.
+    pub async fn set_self_id(&self, self_id: NodeId) {
+        let mut table = self.routing_table.lock().await;
+        *table = RoutingTable::new(self_id);
+    }
+
+    /// Asynchronously inserts a key-value pair into local storage.
     pub async fn insert(&self, key: String, value: String) {
         let mut map = self.store.lock().await;
         map.insert(key, value);
-        // Uncomment for verbose debugging:
-        // log::debug!("Inserted key-value pair into DHT.");
     }
 
-    /// Asynchronously retrieves the value associated with a key, if present.
+    /// Asynchronously retrieves the value associated with a key, if present
+    /// in local storage.
     pub async fn lookup(&self, key: &str) -> Option<String> {
         let map = self.store.lock().await;
         map.get(key).cloned()
     }
+
+    /// Records contact with a peer, returning a stale peer to ping if the
+    /// peer's bucket is full (see `RoutingTable::observe`).
+    pub async fn observe_peer(&self, id: NodeId, addr: String) -> Option<Peer> {
+        let mut table = self.routing_table.lock().await;
+        table.observe(id, addr)
+    }
+
+    /// Admits `(id, addr)` in place of the stale peer evicted from its
+    /// bucket, after that peer has failed a liveness `Ping` (see
+    /// `RoutingTable::evict_stale`).
+    pub async fn evict_stale_peer(&self, id: NodeId, addr: String) {
+        let replacement = Peer { id, addr, last_seen: Instant::now() };
+        let mut table = self.routing_table.lock().await;
+        table.evict_stale(replacement);
+    }
+
+    /// Returns up to `count` known peers closest to `target`.
+    pub async fn closest_peers(&self, target: &NodeId, count: usize) -> Vec<Peer> {
+        let table = self.routing_table.lock().await;
+        table.closest(target, count)
+    }
+
+    /// Removes a peer from the routing table outright, e.g. once
+    /// `membership::GLOBAL_MEMBERSHIP` has declared it `Dead`.
+    pub async fn remove_peer(&self, id: NodeId) {
+        let mut table = self.routing_table.lock().await;
+        table.remove(&id);
+    }
 }
 
 // Create a global DHT instance for use across the application.
 lazy_static! {
     pub static ref GLOBAL_DHT: DHT = DHT::new();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_to_self_is_zero() {
+        let id = sha256_id(b"127.0.0.1:9000");
+        assert_eq!(distance(&id, &id), [0u8; 32]);
+    }
+
+    #[test]
+    fn distance_is_symmetric() {
+        let a = sha256_id(b"127.0.0.1:9000");
+        let b = sha256_id(b"127.0.0.1:9001");
+        assert_eq!(distance(&a, &b), distance(&b, &a));
+    }
+
+    #[test]
+    fn bucket_index_is_none_for_own_id() {
+        let id = sha256_id(b"127.0.0.1:9000");
+        assert_eq!(bucket_index(&distance(&id, &id)), None);
+    }
+
+    #[test]
+    fn bucket_index_is_255_for_differing_high_bit() {
+        let a = [0u8; 32];
+        let mut b = [0u8; 32];
+        b[0] = 0x80;
+        assert_eq!(bucket_index(&distance(&a, &b)), Some(255));
+    }
+
+    #[test]
+    fn bucket_index_is_0_for_differing_low_bit() {
+        let a = [0u8; 32];
+        let mut b = [0u8; 32];
+        b[31] = 0x01;
+        assert_eq!(bucket_index(&distance(&a, &b)), Some(0));
+    }
+
+    #[test]
+    fn closest_sorts_nearest_first_and_honors_count() {
+        let self_id = [0u8; 32];
+        let mut table = RoutingTable::new(self_id);
+        for i in 1..10u8 {
+            let mut id = [0u8; 32];
+            id[31] = i;
+            table.observe(id, format!("127.0.0.1:{}", 9000 + i as u16));
+        }
+
+        let mut target = [0u8; 32];
+        target[31] = 3;
+        let closest = table.closest(&target, 2);
+
+        assert_eq!(closest.len(), 2);
+        assert_eq!(closest[0].addr, "127.0.0.1:9003");
+        assert!(distance(&closest[0].id, &target) <= distance(&closest[1].id, &target));
+    }
+
+    #[test]
+    fn evict_stale_replaces_the_oldest_entry_in_its_bucket() {
+        let self_id = [0u8; 32];
+        let mut table = RoutingTable::new(self_id);
+
+        let mut full_bucket_ids = Vec::new();
+        for i in 0..K {
+            let mut id = [0u8; 32];
+            id[0] = 0x80;
+            id[31] = i as u8;
+            full_bucket_ids.push(id);
+            assert!(table.observe(id, format!("peer-{}", i)).is_none());
+        }
+
+        let mut overflow_id = [0u8; 32];
+        overflow_id[0] = 0x80;
+        overflow_id[31] = K as u8;
+        let stale = table.observe(overflow_id, "peer-overflow".to_string()).expect("bucket is full");
+        assert_eq!(stale.id, full_bucket_ids[0]);
+
+        table.evict_stale(Peer { id: overflow_id, addr: "peer-overflow".to_string(), last_seen: Instant::now() });
+        let all = table.closest(&self_id, K + 1);
+        assert!(!all.iter().any(|p| p.id == full_bucket_ids[0]));
+        assert!(all.iter().any(|p| p.id == overflow_id));
+    }
+
+    #[test]
+    fn remove_drops_the_peer_from_its_bucket() {
+        let self_id = [0u8; 32];
+        let mut table = RoutingTable::new(self_id);
+        let mut id = [0u8; 32];
+        id[31] = 0x01;
+        table.observe(id, "127.0.0.1:9001".to_string());
+        assert!(table.closest(&self_id, 10).iter().any(|p| p.id == id));
+
+        table.remove(&id);
+        assert!(!table.closest(&self_id, 10).iter().any(|p| p.id == id));
+    }
+}
@@ -0,0 +1,68 @@
+/*
+    framing.rs
+    ----------------------------------------------------------------------------
+    Length-prefixed message framing for peer connections.
+
+    Features:
+      - Each frame is a 4-byte big-endian length prefix followed by exactly
+        that many bytes of payload (opaque to this module -- `crypto.rs`
+        decides what goes inside).
+      - Enforces a maximum frame size on the read side so a peer can't make
+        us allocate an unbounded buffer by lying about a frame's length.
+
+    Developer Notes:
+      - `read_frame` returns `Ok(None)` on a clean EOF between frames, so a
+        caller can loop over multiple messages per connection and tell a
+        graceful close apart from a connection that died mid-frame.
+    ----------------------------------------------------------------------------
+*/
+
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Default ceiling on a single frame's payload size, in bytes.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024; // 16 MiB
+
+/// Writes `payload` as a single length-prefixed frame.
+pub async fn write_frame<W>(writer: &mut W, payload: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame payload too large to encode"))?;
+    writer.write_all(&len.to_be_bytes()).await?;
+    writer.write_all(payload).await?;
+    Ok(())
+}
+
+/// Reads a single length-prefixed frame, rejecting anything over
+/// `max_size` before allocating a buffer for it.
+///
+/// Returns `Ok(None)` if the connection was closed cleanly before the next
+/// frame's length prefix began; an error if it closed partway through one.
+pub async fn read_frame<R>(reader: &mut R, max_size: usize) -> io::Result<Option<Vec<u8>>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut len_buf = [0u8; 4];
+    let mut filled = 0usize;
+    while filled < len_buf.len() {
+        match reader.read(&mut len_buf[filled..]).await? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed mid length-prefix")),
+            n => filled += n,
+        }
+    }
+
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len > max_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {} bytes exceeds max frame size of {}", len, max_size),
+        ));
+    }
+
+    let mut buffer = vec![0u8; len];
+    reader.read_exact(&mut buffer).await?;
+    Ok(Some(buffer))
+}
@@ -15,16 +15,16 @@
 
 use clap::{Parser, Subcommand};
 use log::{info, error};
-use tokio::net::TcpStream;
-use tokio::io::{BufReader, AsyncBufReadExt, AsyncWriteExt};
-use serde_json;
 
+mod crypto;
 mod dht;
-mod network;
 mod file_manager;
+mod framing;
+mod membership;
+mod network;
 mod protocol;
 
-use protocol::Message;
+const BOOTSTRAP_ADDR: &str = "127.0.0.1:8080";
 
 /// RustDHTShare: A Distributed File Sharing Platform in Rust.
 #[derive(Parser)]
@@ -67,6 +67,23 @@ enum NodeAction {
         /// The key to lookup.
         key: String,
     },
+    /// Split a file into content-addressed chunks and publish its manifest.
+    Publish {
+        /// Path to the file to publish.
+        path: String,
+    },
+    /// List the peers the bootstrap node currently believes are alive.
+    Peers,
+    /// Fetch a published file chunk by chunk, verifying against its manifest.
+    Fetch {
+        /// The file's manifest root hash, as printed by `publish`.
+        file_id: String,
+        /// Address of the peer serving the file.
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        peer: String,
+        /// Where to write the downloaded file.
+        output: String,
+    },
 }
 
 #[tokio::main]
@@ -83,81 +100,43 @@ async fn main() {
             network::start_server(port).await;
         },
         Commands::Node { action } => {
+            // Bind a real, dialable listener before doing anything else, so
+            // this node's identity in the DHT/membership is somewhere other
+            // peers can actually reach it, not a made-up placeholder.
+            let self_addr = network::start_node_listener().await.expect("failed to bind node listener");
+
             match action {
                 // If no subcommand is provided, join the network by default.
                 None | Some(NodeAction::Join) => {
-                    info!("Starting node: joining network at 127.0.0.1:8080...");
-                    network::join_network("127.0.0.1:8080").await;
+                    info!("Starting node: joining network at {}...", BOOTSTRAP_ADDR);
+                    network::join_network(BOOTSTRAP_ADDR, &self_addr).await;
                 },
                 Some(NodeAction::Store { key, value }) => {
                     info!("Storing key-value pair: {} -> {}", key, value);
-                    store_key_value(key, value).await;
+                    network::store_value(BOOTSTRAP_ADDR, key, value, &self_addr).await;
                 },
                 Some(NodeAction::Lookup { key }) => {
                     info!("Looking up key: {}", key);
-                    lookup_key(key).await;
+                    network::lookup_value(BOOTSTRAP_ADDR, key, &self_addr).await;
+                },
+                Some(NodeAction::Peers) => {
+                    info!("Listing peers known to {}...", BOOTSTRAP_ADDR);
+                    network::list_peers(BOOTSTRAP_ADDR, &self_addr).await;
+                },
+                Some(NodeAction::Publish { path }) => {
+                    info!("Publishing file: {}", path);
+                    match network::publish_file(BOOTSTRAP_ADDR, std::path::Path::new(&path), &self_addr).await {
+                        Ok(file_id) => info!("Published {} as file_id {}", path, file_id),
+                        Err(e) => error!("Failed to publish {}: {:?}", path, e),
+                    }
+                },
+                Some(NodeAction::Fetch { file_id, peer, output }) => {
+                    info!("Fetching file {} from {} into {}", file_id, peer, output);
+                    if let Err(e) = network::fetch_file(&peer, &file_id, std::path::Path::new(&output), &self_addr).await {
+                        error!("Failed to fetch {}: {:?}", file_id, e);
+                    }
                 },
             }
         },
     }
 }
-
-/// Connects to the bootstrap node and sends a Store command.
-/// Logs detailed error information for debugging.
-async fn store_key_value(key: String, value: String) {
-    match TcpStream::connect("127.0.0.1:8080").await {
-        Ok(mut stream) => {
-            let msg = Message::Store { key, value };
-            let msg_json = serde_json::to_string(&msg).unwrap();
-            if let Err(e) = stream.write_all(msg_json.as_bytes()).await {
-                error!("Error sending store message: {:?}", e);
-                return;
-            }
-            if let Err(e) = stream.write_all(b"\n").await {
-                error!("Error sending newline: {:?}", e);
-                return;
-            }
-            let mut reader = BufReader::new(stream);
-            let mut response = String::new();
-            if let Err(e) = reader.read_line(&mut response).await {
-                error!("Error reading response: {:?}", e);
-                return;
-            }
-            match serde_json::from_str::<Message>(&response) {
-                Ok(resp_msg) => info!("Store response: {:?}", resp_msg),
-                Err(e) => error!("Failed to parse response: {:?}", e),
-            }
-        },
-        Err(e) => error!("Could not connect to bootstrap node: {:?}", e),
-    }
-}
-
-/// Connects to the bootstrap node and sends a Lookup command.
-/// Logs errors and prints the response.
-async fn lookup_key(key: String) {
-    match TcpStream::connect("127.0.0.1:8080").await {
-        Ok(mut stream) => {
-            let msg = Message::Lookup { key };
-            let msg_json = serde_json::to_string(&msg).unwrap();
-            if let Err(e) = stream.write_all(msg_json.as_bytes()).await {
-                error!("Error sending lookup message: {:?}", e);
-                return;
-            }
-            if let Err(e) = stream.write_all(b"\n").await {
-                error!("Error sending newline: {:?}", e);
-                return;
-            }
-            let mut reader = BufReader::new(stream);
-            let mut response = String::new();
-            if let Err(e) = reader.read_line(&mut response).await {
-                error!("Error reading response: {:?}", e);
-                return;
-            }
-            match serde_json::from_str::<Message>(&response) {
-                Ok(resp_msg) => info!("Lookup response: {:?}", resp_msg),
-                Err(e) => error!("Failed to parse response: {:?}", e),
-            }
-        },
-        Err(e) => error!("Could not connect to bootstrap node: {:?}", e),
-    }
-}
@@ -0,0 +1,120 @@
+/*
+    membership.rs
+    ----------------------------------------------------------------------------
+    Tracks which peers in the network are currently reachable.
+
+    Features:
+      - A shared table of known peer addresses, each with a last-seen time and
+        a liveness status (Alive, Suspect, Dead).
+      - Failure detection is driven externally by `network.rs`'s heartbeat
+        loop: each missed `Pong` downgrades a peer's status, and a successful
+        `Pong` resets it straight back to Alive.
+
+    Developer Notes:
+      - `GLOBAL_MEMBERSHIP` is a lazy_static global, mirroring `GLOBAL_DHT`.
+      - This table is deliberately separate from the Kademlia routing table in
+        `dht.rs`: the routing table answers "who is closest to X", while this
+        answers "who do we currently believe is alive", which is what drives
+        heartbeats and `Join` gossip.
+    ----------------------------------------------------------------------------
+*/
+
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+/// Number of consecutive missed `Pong` replies before a `Suspect` peer is
+/// marked `Dead`.
+pub const MAX_MISSED_HEARTBEATS: u32 = 3;
+
+/// Maximum number of live peers gossiped back in a single `Peers` reply.
+pub const GOSSIP_SAMPLE_SIZE: usize = 5;
+
+/// A peer's believed liveness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerStatus {
+    Alive,
+    Suspect,
+    Dead,
+}
+
+/// What we know about one peer's membership state.
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub addr: String,
+    pub status: PeerStatus,
+    pub last_seen: Instant,
+    missed_heartbeats: u32,
+}
+
+/// Table of known peers and their liveness, shared across the node.
+pub struct Membership {
+    peers: Mutex<HashMap<String, Member>>,
+}
+
+impl Membership {
+    pub fn new() -> Self {
+        Membership { peers: Mutex::new(HashMap::new()) }
+    }
+
+    /// Records a successful contact with `addr` (a `Join`, a heartbeat
+    /// `Pong`, or a gossiped introduction), marking it `Alive` and resetting
+    /// its missed-heartbeat count.
+    pub async fn mark_alive(&self, addr: String) {
+        let mut peers = self.peers.lock().await;
+        let member = peers.entry(addr.clone()).or_insert_with(|| Member {
+            addr,
+            status: PeerStatus::Alive,
+            last_seen: Instant::now(),
+            missed_heartbeats: 0,
+        });
+        member.status = PeerStatus::Alive;
+        member.last_seen = Instant::now();
+        member.missed_heartbeats = 0;
+    }
+
+    /// Records a missed heartbeat for `addr`, downgrading it to `Suspect` and
+    /// then `Dead` after `MAX_MISSED_HEARTBEATS` consecutive misses. Returns
+    /// the peer's new status, or `None` if `addr` isn't known.
+    pub async fn record_missed_heartbeat(&self, addr: &str) -> Option<PeerStatus> {
+        let mut peers = self.peers.lock().await;
+        let member = peers.get_mut(addr)?;
+        member.missed_heartbeats += 1;
+        member.status = if member.missed_heartbeats >= MAX_MISSED_HEARTBEATS {
+            PeerStatus::Dead
+        } else {
+            PeerStatus::Suspect
+        };
+        Some(member.status)
+    }
+
+    /// Returns the addresses of every peer currently believed `Alive`.
+    pub async fn live_addrs(&self) -> Vec<String> {
+        let peers = self.peers.lock().await;
+        peers.values().filter(|m| m.status == PeerStatus::Alive).map(|m| m.addr.clone()).collect()
+    }
+
+    /// Returns up to `count` live peer addresses, excluding `exclude`, for
+    /// gossiping to a newly joined (or newly discovered) peer.
+    pub async fn sample_live(&self, count: usize, exclude: &str) -> Vec<String> {
+        let peers = self.peers.lock().await;
+        peers
+            .values()
+            .filter(|m| m.status == PeerStatus::Alive && m.addr != exclude)
+            .take(count)
+            .map(|m| m.addr.clone())
+            .collect()
+    }
+
+    /// Returns every peer this node currently knows about, alive or not.
+    pub async fn all(&self) -> Vec<Member> {
+        let peers = self.peers.lock().await;
+        peers.values().cloned().collect()
+    }
+}
+
+// Create a global membership table for use across the application.
+lazy_static! {
+    pub static ref GLOBAL_MEMBERSHIP: Membership = Membership::new();
+}
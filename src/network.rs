@@ -2,35 +2,455 @@
     network.rs
     ----------------------------------------------------------------------------
     Handles networking operations for RustDHTShare.
-    
+
     - Listens for incoming TCP connections on the bootstrap node.
-    - Processes JSON-encoded messages (Store, Lookup, Join, etc.).
+    - Processes MessagePack-encoded messages (Store, Lookup, Join, FindNode, etc.).
     - Uses Tokio for asynchronous I/O and logs all significant events.
-    
+    - Every connection begins with an X25519/AES-256-GCM handshake (see
+      `crypto.rs`), followed immediately by a `Hello` exchange that
+      negotiates protocol version and capabilities (see `protocol.rs`);
+      all `Message`s after that are encrypted. The server answers every
+      message type whatever capabilities were negotiated; `fetch_file`
+      checks the negotiated set before requesting chunks.
+    - Implements the Kademlia iterative lookup used to locate the nodes
+      responsible for a key before storing or fetching its value.
+    - Serves and fetches chunked file transfers (see `file_manager.rs`),
+      verifying every chunk against its manifest hash.
+    - Each connection carries length-prefixed, MessagePack-encoded frames
+      (see `framing.rs`) and can carry more than one message in sequence.
+    - Maintains full-mesh peer membership (see `membership.rs`): `Join`
+      gossips the joiner to every other known live peer, and a background
+      heartbeat loop pings known peers, marking them Suspect then Dead after
+      consecutive missed `Pong` replies.
+
     Debugged thoroughly to ensure robust error handling and clarity.
     ----------------------------------------------------------------------------
 */
 
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use crate::protocol::Message;
-use serde_json;
+use crate::crypto::{self, SecureChannel};
+use crate::dht::{self, NodeId};
+use crate::file_manager::{self, Manifest};
+use crate::membership::{self, PeerStatus};
+use crate::protocol::{self, Message, PeerInfo};
 use std::error::Error;
-use log::{info, error};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use log::{info, error, warn};
+
+/// How often the background heartbeat loop pings known peers.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Directory where this node keeps the raw bytes of files it can serve.
+/// Files are named after their manifest root hash.
+const SHARED_DIR: &str = "shared_files";
+
+fn shared_path(file_id: &str) -> PathBuf {
+    Path::new(SHARED_DIR).join(file_id)
+}
+
+/// Loads the manifest for `file_id` from local DHT storage, if known.
+async fn load_manifest(file_id: &str) -> Option<Manifest> {
+    let json = crate::dht::GLOBAL_DHT.lookup(file_id).await?;
+    match serde_json::from_str(&json) {
+        Ok(manifest) => Some(manifest),
+        Err(e) => {
+            warn!("Stored value for {} is not a valid manifest: {:?}", file_id, e);
+            None
+        },
+    }
+}
+
+/// This node's own routable identity, derived from `self_addr` (the address
+/// returned by `start_server`/`start_node_listener`, i.e. somewhere this
+/// node is actually reachable). Never construct a `PeerInfo` with a made-up
+/// address: anything fed to `observe_peer`/membership ends up believed
+/// reachable by every other node in the mesh.
+fn local_identity(self_addr: &str) -> PeerInfo {
+    PeerInfo { id: node_id_hex(&dht::id_from_addr(self_addr)), addr: self_addr.to_string() }
+}
+
+fn node_id_hex(id: &NodeId) -> String {
+    hex::encode(id)
+}
+
+#[derive(Debug)]
+struct BadNodeId(String);
+
+impl fmt::Display for BadNodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "not a valid 256-bit node id: {}", self.0)
+    }
+}
+impl Error for BadNodeId {}
+
+fn node_id_from_hex(s: &str) -> Result<NodeId, Box<dyn Error + Send + Sync>> {
+    let bytes = hex::decode(s).map_err(|_| BadNodeId(s.to_string()))?;
+    if bytes.len() != 32 {
+        return Err(Box::new(BadNodeId(s.to_string())));
+    }
+    let mut id = [0u8; 32];
+    id.copy_from_slice(&bytes);
+    Ok(id)
+}
+
+/// Returns the k closest known peers to `target` as wire-format `PeerInfo`,
+/// excluding `exclude` (the requester itself, so a node never gets handed
+/// back its own address as if it were some other peer to route to or
+/// replicate onto).
+async fn closest_peer_infos(target: &NodeId, exclude: &str) -> Vec<PeerInfo> {
+    crate::dht::GLOBAL_DHT
+        .closest_peers(target, dht::K)
+        .await
+        .into_iter()
+        .filter(|p| p.addr != exclude)
+        .map(|p| PeerInfo { id: node_id_hex(&p.id), addr: p.addr })
+        .collect()
+}
+
+/// Returns up to `membership::GOSSIP_SAMPLE_SIZE` peers this node currently
+/// believes are alive (excluding `exclude`), as wire-format `PeerInfo`.
+async fn live_peer_infos(exclude: &str) -> Vec<PeerInfo> {
+    membership::GLOBAL_MEMBERSHIP
+        .sample_live(membership::GOSSIP_SAMPLE_SIZE, exclude)
+        .await
+        .into_iter()
+        .map(|addr| PeerInfo { id: node_id_hex(&dht::id_from_addr(&addr)), addr })
+        .collect()
+}
+
+/// Tells every other currently-known live peer about `new_addr`, so a single
+/// `Join` propagates through the mesh instead of only reaching the node it
+/// dialed. Runs in the background so the joiner isn't kept waiting on it.
+fn gossip_new_peer(new_addr: String, self_addr: String) {
+    tokio::spawn(async move {
+        let node_id = node_id_hex(&dht::id_from_addr(&new_addr));
+        for peer_addr in membership::GLOBAL_MEMBERSHIP.live_addrs().await {
+            if peer_addr == new_addr {
+                continue;
+            }
+            let msg = Message::Join { node_id: node_id.clone(), addr: new_addr.clone() };
+            if let Err(e) = query_peer(&peer_addr, msg, &self_addr).await {
+                warn!("Gossiping new peer {} to {} failed: {:?}", new_addr, peer_addr, e);
+            }
+        }
+    });
+}
+
+/// Background task: periodically pings every known peer and downgrades its
+/// membership status after consecutive missed `Pong` replies, per
+/// `membership::MAX_MISSED_HEARTBEATS`. Once a peer is declared `Dead`, it is
+/// also evicted from `dht::GLOBAL_DHT`'s routing table, so the two peer
+/// tables stay in sync instead of the routing table holding onto a zombie
+/// that membership has already given up on.
+async fn heartbeat_loop(self_addr: String) {
+    let mut ticker = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        ticker.tick().await;
+        for member in membership::GLOBAL_MEMBERSHIP.all().await {
+            if member.status == PeerStatus::Dead {
+                continue;
+            }
+            match query_peer(&member.addr, Message::Ping, &self_addr).await {
+                Ok(Message::Pong) => membership::GLOBAL_MEMBERSHIP.mark_alive(member.addr).await,
+                Ok(other) => warn!("Unexpected reply to heartbeat Ping from {}: {:?}", member.addr, other),
+                Err(e) => {
+                    if let Some(status) = membership::GLOBAL_MEMBERSHIP.record_missed_heartbeat(&member.addr).await {
+                        warn!("Heartbeat to {} failed, now {:?}: {:?}", member.addr, status, e);
+                        if status == PeerStatus::Dead {
+                            crate::dht::GLOBAL_DHT.remove_peer(dht::id_from_addr(&member.addr)).await;
+                        }
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Records contact with a peer. If its bucket is full, pings the stalest
+/// entry and evicts it only if it fails to respond. Also marks the peer
+/// alive in `membership::GLOBAL_MEMBERSHIP`, so a contact learned purely via
+/// `FindNode`/`FindValue` still enters the heartbeat loop instead of being
+/// tracked only in the routing table (see `heartbeat_loop`, which evicts
+/// from the routing table once membership declares a peer `Dead`).
+async fn observe_peer(peer: &PeerInfo, self_addr: &str) {
+    let id = match node_id_from_hex(&peer.id) {
+        Ok(id) => id,
+        Err(e) => {
+            warn!("Ignoring peer with malformed id {}: {:?}", peer.id, e);
+            return;
+        },
+    };
+    membership::GLOBAL_MEMBERSHIP.mark_alive(peer.addr.clone()).await;
+    if let Some(stale) = crate::dht::GLOBAL_DHT.observe_peer(id, peer.addr.clone()).await {
+        if query_peer(&stale.addr, Message::Ping, self_addr).await.is_err() {
+            info!("Evicting unresponsive peer {} from routing table", stale.addr);
+            crate::dht::GLOBAL_DHT.evict_stale_peer(id, peer.addr.clone()).await;
+        }
+    }
+}
+
+/// Sends our `Hello` and validates the peer's reply, returning the
+/// capabilities both sides advertised in common. Used on every connection,
+/// right after the encryption handshake and before any other message.
+async fn negotiate<R, W>(reader: &mut R, writer: &mut W, channel: &SecureChannel, node_id: &str) -> Result<Vec<String>, Box<dyn Error + Send + Sync>>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let hello = Message::Hello {
+        protocol_version: protocol::PROTOCOL_VERSION,
+        capabilities: protocol::local_capabilities(),
+        node_id: node_id.to_string(),
+    };
+    crypto::send_message(writer, channel, &hello).await?;
+
+    match crypto::recv_message(reader, channel).await? {
+        Some(Message::Hello { protocol_version, capabilities, node_id: peer_id }) => {
+            if protocol_version < protocol::MIN_SUPPORTED_PROTOCOL_VERSION {
+                let reason = format!(
+                    "protocol version {} is older than the minimum supported version {}",
+                    protocol_version, protocol::MIN_SUPPORTED_PROTOCOL_VERSION
+                );
+                crypto::send_message(writer, channel, &Message::NegotiationError { reason: reason.clone() }).await?;
+                return Err(reason.into());
+            }
+            let ours = protocol::local_capabilities();
+            let negotiated: Vec<String> = capabilities.into_iter().filter(|c| ours.contains(c)).collect();
+            info!("Negotiated with {}: protocol v{}, capabilities {:?}", peer_id, protocol_version, negotiated);
+            Ok(negotiated)
+        },
+        Some(Message::NegotiationError { reason }) => Err(format!("peer rejected handshake: {}", reason).into()),
+        Some(other) => Err(format!("expected Hello, got {:?}", other).into()),
+        None => Err("peer closed the connection during negotiation".into()),
+    }
+}
+
+/// Server side of the handshake: the peer speaks first. Reads their `Hello`,
+/// validates it, and replies with ours (or a `NegotiationError` if the
+/// peer's version is too old).
+async fn negotiate_server<R, W>(reader: &mut R, writer: &mut W, channel: &SecureChannel, node_id: &str) -> Result<Vec<String>, Box<dyn Error + Send + Sync>>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    match crypto::recv_message(reader, channel).await? {
+        Some(Message::Hello { protocol_version, capabilities, node_id: peer_id }) => {
+            if protocol_version < protocol::MIN_SUPPORTED_PROTOCOL_VERSION {
+                let reason = format!(
+                    "protocol version {} is older than the minimum supported version {}",
+                    protocol_version, protocol::MIN_SUPPORTED_PROTOCOL_VERSION
+                );
+                crypto::send_message(writer, channel, &Message::NegotiationError { reason: reason.clone() }).await?;
+                return Err(reason.into());
+            }
+            let ours = protocol::local_capabilities();
+            let negotiated: Vec<String> = capabilities.into_iter().filter(|c| ours.contains(c)).collect();
+            let reply = Message::Hello { protocol_version: protocol::PROTOCOL_VERSION, capabilities: ours, node_id: node_id.to_string() };
+            crypto::send_message(writer, channel, &reply).await?;
+            info!("Negotiated with {}: protocol v{}, capabilities {:?}", peer_id, protocol_version, negotiated);
+            Ok(negotiated)
+        },
+        Some(other) => {
+            let reason = "expected Hello as the first message".to_string();
+            crypto::send_message(writer, channel, &Message::NegotiationError { reason: reason.clone() }).await?;
+            Err(format!("{} (got {:?})", reason, other).into())
+        },
+        None => Err("peer closed the connection before sending Hello".into()),
+    }
+}
+
+/// Connects to `addr`, performs the encryption handshake and capability
+/// negotiation, and returns the still-open connection halves. `self_addr` is
+/// this node's own dialable address, advertised in the `Hello`.
+async fn connect_and_negotiate(addr: &str, self_addr: &str) -> Result<(OwnedReadHalf, OwnedWriteHalf, SecureChannel, Vec<String>), Box<dyn Error + Send + Sync>> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let channel = SecureChannel::connect(&mut stream).await?;
+    let (mut reader, mut writer) = stream.into_split();
+    let caps = negotiate(&mut reader, &mut writer, &channel, &local_identity(self_addr).id).await?;
+    Ok((reader, writer, channel, caps))
+}
+
+/// Connects to `addr`, negotiates, sends `msg`, and returns the single
+/// encrypted reply.
+async fn query_peer(addr: &str, msg: Message, self_addr: &str) -> Result<Message, Box<dyn Error + Send + Sync>> {
+    let (mut reader, mut writer, channel, _caps) = connect_and_negotiate(addr, self_addr).await?;
+    crypto::send_message(&mut writer, &channel, &msg).await?;
+    match crypto::recv_message(&mut reader, &channel).await? {
+        Some(reply) => Ok(reply),
+        None => Err("peer closed the connection before replying".into()),
+    }
+}
+
+/// Kademlia iterative lookup. Starting from `seed_addr`, queries the
+/// alpha=3 closest not-yet-queried peers one after another (the queries of
+/// a round are not run in parallel), merges any newly discovered peers into
+/// the shortlist, and repeats until a round turns up nothing closer to
+/// `target` than what is already known.
+async fn iterative_lookup(seed_addr: &str, target: NodeId, self_addr: &str) -> Vec<PeerInfo> {
+    let target_hex = node_id_hex(&target);
+    let mut shortlist: Vec<PeerInfo> = vec![PeerInfo {
+        id: node_id_hex(&dht::id_from_addr(seed_addr)),
+        addr: seed_addr.to_string(),
+    }];
+    let mut queried: std::collections::HashSet<String> = std::collections::HashSet::new();
+
+    loop {
+        let candidates: Vec<PeerInfo> = shortlist
+            .iter()
+            .filter(|p| !queried.contains(&p.addr))
+            .take(dht::ALPHA)
+            .cloned()
+            .collect();
+        if candidates.is_empty() {
+            break;
+        }
+
+        let closest_before = shortlist
+            .iter()
+            .filter_map(|p| node_id_from_hex(&p.id).ok())
+            .map(|id| dht::distance(&id, &target))
+            .min();
+        for candidate in candidates {
+            queried.insert(candidate.addr.clone());
+            let req = Message::FindNode { requester: local_identity(self_addr), target: target_hex.clone() };
+            match query_peer(&candidate.addr, req, self_addr).await {
+                Ok(Message::NodesFound { peers }) => {
+                    for p in peers {
+                        if !shortlist.iter().any(|sp| sp.id == p.id) {
+                            shortlist.push(p);
+                        }
+                    }
+                },
+                Ok(other) => warn!("Unexpected reply to FindNode from {}: {:?}", candidate.addr, other),
+                Err(e) => warn!("FindNode to {} failed: {:?}", candidate.addr, e),
+            }
+        }
+
+        shortlist.sort_by_key(|p| {
+            node_id_from_hex(&p.id)
+                .map(|id| dht::distance(&id, &target))
+                .unwrap_or([0xff; 32])
+        });
+        shortlist.truncate(dht::K);
+
+        let closest_after = shortlist
+            .first()
+            .and_then(|p| node_id_from_hex(&p.id).ok())
+            .map(|id| dht::distance(&id, &target));
+        if closest_after >= closest_before {
+            break;
+        }
+    }
+
+    shortlist
+}
+
+/// Like `iterative_lookup`, but stops early and returns the value as soon as
+/// any queried peer reports it via `ValueFound`.
+async fn iterative_find_value(seed_addr: &str, key: &str, self_addr: &str) -> (Vec<PeerInfo>, Option<String>) {
+    let target = dht::id_from_key(key);
+    let mut shortlist: Vec<PeerInfo> = vec![PeerInfo {
+        id: node_id_hex(&dht::id_from_addr(seed_addr)),
+        addr: seed_addr.to_string(),
+    }];
+    let mut queried: std::collections::HashSet<String> = std::collections::HashSet::new();
+
+    loop {
+        let candidates: Vec<PeerInfo> = shortlist
+            .iter()
+            .filter(|p| !queried.contains(&p.addr))
+            .take(dht::ALPHA)
+            .cloned()
+            .collect();
+        if candidates.is_empty() {
+            break;
+        }
+
+        let closest_before = shortlist
+            .iter()
+            .filter_map(|p| node_id_from_hex(&p.id).ok())
+            .map(|id| dht::distance(&id, &target))
+            .min();
+        for candidate in candidates {
+            queried.insert(candidate.addr.clone());
+            let req = Message::FindValue { requester: local_identity(self_addr), key: key.to_string() };
+            match query_peer(&candidate.addr, req, self_addr).await {
+                Ok(Message::ValueFound { value, .. }) => return (shortlist, Some(value)),
+                Ok(Message::NodesFound { peers }) => {
+                    for p in peers {
+                        if !shortlist.iter().any(|sp| sp.id == p.id) {
+                            shortlist.push(p);
+                        }
+                    }
+                },
+                Ok(other) => warn!("Unexpected reply to FindValue from {}: {:?}", candidate.addr, other),
+                Err(e) => warn!("FindValue to {} failed: {:?}", candidate.addr, e),
+            }
+        }
+
+        shortlist.sort_by_key(|p| {
+            node_id_from_hex(&p.id)
+                .map(|id| dht::distance(&id, &target))
+                .unwrap_or([0xff; 32])
+        });
+        shortlist.truncate(dht::K);
+
+        let closest_after = shortlist
+            .first()
+            .and_then(|p| node_id_from_hex(&p.id).ok())
+            .map(|id| dht::distance(&id, &target));
+        if closest_after >= closest_before {
+            break;
+        }
+    }
+
+    (shortlist, None)
+}
 
 /// Starts the bootstrap node server on the given port.
 /// Continuously accepts incoming connections and spawns asynchronous tasks.
 pub async fn start_server(port: u16) {
     let addr = format!("0.0.0.0:{}", port);
     let listener = TcpListener::bind(&addr).await.unwrap();
-    info!("Server listening on {}", addr);
 
+    let self_addr = format!("127.0.0.1:{}", port);
+    crate::dht::GLOBAL_DHT.set_self_id(dht::id_from_addr(&self_addr)).await;
+    info!("Server listening on {} (node id {})", addr, node_id_hex(&dht::id_from_addr(&self_addr)));
+
+    tokio::spawn(heartbeat_loop(self_addr.clone()));
+    accept_loop(listener, self_addr).await;
+}
+
+/// Binds an ephemeral listener so a `Node` CLI invocation has a real,
+/// dialable address to advertise as its identity, instead of a placeholder
+/// nothing can connect back to. Returns that address once bound; the accept
+/// loop itself keeps running in the background for as long as the process
+/// does, answering `Ping`s and routing/membership queries like any other
+/// peer.
+pub async fn start_node_listener() -> std::io::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let self_addr = listener.local_addr()?.to_string();
+    crate::dht::GLOBAL_DHT.set_self_id(dht::id_from_addr(&self_addr)).await;
+    info!("Listening on {} (node id {})", self_addr, node_id_hex(&dht::id_from_addr(&self_addr)));
+    tokio::spawn(accept_loop(listener, self_addr.clone()));
+    Ok(self_addr)
+}
+
+/// Accepts connections off `listener` for as long as the process runs,
+/// handling each on its own task. `self_addr` is this node's own dialable
+/// address, passed down to `handle_connection` for the `Hello` it sends.
+async fn accept_loop(listener: TcpListener, self_addr: String) {
     loop {
         match listener.accept().await {
             Ok((socket, addr)) => {
                 info!("New connection from {}", addr);
+                let self_addr = self_addr.clone();
                 tokio::spawn(async move {
-                    if let Err(e) = handle_connection(socket).await {
+                    if let Err(e) = handle_connection(socket, &self_addr).await {
                         error!("Error handling connection from {}: {:?}", addr, e);
                     }
                 });
@@ -41,79 +461,223 @@ pub async fn start_server(port: u16) {
 }
 
 /// Processes an incoming TCP connection.
-/// Reads one JSON message, processes it according to its type, and writes a response.
-async fn handle_connection(mut socket: TcpStream) -> Result<(), Box<dyn Error>> {
-    let (reader, mut writer) = socket.split();
-    let mut buf_reader = BufReader::new(reader);
-    let mut line = String::new();
-
-    // Read a full line representing a complete JSON message.
-    buf_reader.read_line(&mut line).await?;
-    let msg: Message = serde_json::from_str(&line)?;
-    info!("Received message: {:?}", msg);
-
-    // Process the message based on its type.
+/// Performs the encryption handshake, then protocol negotiation, then loops
+/// reading encrypted messages off the connection and replying to each in
+/// turn until the peer closes it.
+async fn handle_connection(mut socket: TcpStream, self_addr: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let channel = SecureChannel::accept(&mut socket).await?;
+    let (mut reader, mut writer) = socket.split();
+
+    let negotiated = negotiate_server(&mut reader, &mut writer, &channel, &local_identity(self_addr).id).await?;
+
+    while let Some(msg) = crypto::recv_message(&mut reader, &channel).await? {
+        info!("Received message: {:?}", msg);
+        let reply = handle_message(msg, &negotiated, self_addr).await;
+        crypto::send_message(&mut writer, &channel, &reply).await?;
+    }
+    Ok(())
+}
+
+/// Processes a single decrypted message and returns the reply to send back.
+/// `_negotiated` holds the capability set agreed on during the connection's
+/// `Hello` exchange, for handlers that need to gate behavior on it.
+/// `self_addr` is this node's own dialable address, needed to dial back out
+/// (gossip, liveness pings) while handling the message.
+async fn handle_message(msg: Message, _negotiated: &[String], self_addr: &str) -> Message {
     match msg {
+        Message::Join { node_id, addr } => {
+            info!("Node {} joined from {}", node_id, addr);
+            // Checked before `observe_peer`, which itself marks the joiner alive.
+            let is_new = !membership::GLOBAL_MEMBERSHIP.all().await.iter().any(|m| m.addr == addr);
+            observe_peer(&PeerInfo { id: node_id_hex(&dht::id_from_addr(&addr)), addr: addr.clone() }, self_addr).await;
+
+            membership::GLOBAL_MEMBERSHIP.mark_alive(addr.clone()).await;
+            if is_new {
+                gossip_new_peer(addr.clone(), self_addr.to_string());
+            }
+
+            Message::Peers { peers: live_peer_infos(&addr).await }
+        },
+        Message::Ping => Message::Pong,
+        Message::ListPeers => Message::Peers { peers: live_peer_infos("").await },
         Message::Store { key, value } => {
             crate::dht::GLOBAL_DHT.insert(key.clone(), value.clone()).await;
             info!("Stored in DHT: {} -> {}", key, value);
-            let reply = Message::Ack;
-            let reply_json = serde_json::to_string(&reply)?;
-            writer.write_all(reply_json.as_bytes()).await?;
-            writer.write_all(b"\n").await?;
+            Message::Ack
         },
         Message::Lookup { key } => {
             if let Some(found_value) = crate::dht::GLOBAL_DHT.lookup(&key).await {
                 info!("Lookup success: {} -> {}", key, found_value);
-                let reply = Message::Store { key, value: found_value };
-                let reply_json = serde_json::to_string(&reply)?;
-                writer.write_all(reply_json.as_bytes()).await?;
-                writer.write_all(b"\n").await?;
+                Message::Store { key, value: found_value }
             } else {
                 info!("Lookup miss: {}", key);
-                let reply = Message::Ack;
-                let reply_json = serde_json::to_string(&reply)?;
-                writer.write_all(reply_json.as_bytes()).await?;
-                writer.write_all(b"\n").await?;
+                Message::Ack
             }
         },
-        // For all other message types, simply acknowledge receipt.
-        _ => {
-            let reply = Message::Ack;
-            let reply_json = serde_json::to_string(&reply)?;
-            writer.write_all(reply_json.as_bytes()).await?;
-            writer.write_all(b"\n").await?;
+        Message::FindNode { requester, target } => {
+            observe_peer(&requester, self_addr).await;
+            match node_id_from_hex(&target) {
+                Ok(target_id) => Message::NodesFound { peers: closest_peer_infos(&target_id, &requester.addr).await },
+                Err(e) => {
+                    warn!("Malformed FindNode target: {:?}", e);
+                    Message::NodesFound { peers: Vec::new() }
+                },
+            }
+        },
+        Message::FindValue { requester, key } => {
+            observe_peer(&requester, self_addr).await;
+            if let Some(value) = crate::dht::GLOBAL_DHT.lookup(&key).await {
+                Message::ValueFound { key, value }
+            } else {
+                let target = dht::id_from_key(&key);
+                Message::NodesFound { peers: closest_peer_infos(&target, &requester.addr).await }
+            }
+        },
+        Message::FileRequest { file_id, chunk_index } => {
+            match load_manifest(&file_id).await {
+                Some(manifest) => match file_manager::read_chunk(&shared_path(&file_id), &manifest, chunk_index) {
+                    Ok(data) => Message::FileData { file_id, chunk_index, data },
+                    Err(e) => {
+                        warn!("Failed to read chunk {} of {}: {:?}", chunk_index, file_id, e);
+                        Message::Ack
+                    },
+                },
+                None => {
+                    warn!("No manifest known locally for file {}", file_id);
+                    Message::Ack
+                },
+            }
         },
+        // For all other message types, simply acknowledge receipt.
+        _ => Message::Ack,
     }
-    Ok(())
 }
 
-/// Connects to the bootstrap node and sends a Join message.
-/// Logs the response received from the bootstrap node.
-pub async fn join_network(bootstrap_addr: &str) {
-    match TcpStream::connect(bootstrap_addr).await {
-        Ok(mut stream) => {
-            info!("Connected to bootstrap node at {}", bootstrap_addr);
-            let join_msg = Message::Join { node_id: "node1".to_string() };
-            let msg_json = serde_json::to_string(&join_msg).unwrap();
-            if let Err(e) = stream.write_all(msg_json.as_bytes()).await {
-                error!("Error sending join message: {:?}", e);
-            }
-            if let Err(e) = stream.write_all(b"\n").await {
-                error!("Error sending newline: {:?}", e);
+/// Connects to the bootstrap node, performs the encryption handshake, and
+/// sends a Join message. Populates this node's membership table with the
+/// bootstrap node and the peers it gossips back. `self_addr` is this node's
+/// own dialable address (see `start_node_listener`), advertised in the Join.
+pub async fn join_network(bootstrap_addr: &str, self_addr: &str) {
+    let join_msg = Message::Join { node_id: local_identity(self_addr).id, addr: self_addr.to_string() };
+    match query_peer(bootstrap_addr, join_msg, self_addr).await {
+        Ok(Message::Peers { peers }) => {
+            info!("Joined network via {}; {} peer(s) known: {:?}", bootstrap_addr, peers.len(), peers);
+            membership::GLOBAL_MEMBERSHIP.mark_alive(bootstrap_addr.to_string()).await;
+            for peer in peers {
+                membership::GLOBAL_MEMBERSHIP.mark_alive(peer.addr).await;
             }
-            let mut reader = BufReader::new(stream);
-            let mut response = String::new();
-            if let Err(e) = reader.read_line(&mut response).await {
-                error!("Error reading response: {:?}", e);
-            }
-            match serde_json::from_str::<Message>(&response) {
-                Ok(resp) => info!("Received response: {:?}", resp),
-                Err(e) => error!("Failed to parse response: {:?}", e),
+        },
+        Ok(resp) => info!("Received response: {:?}", resp),
+        Err(e) => error!("Could not join network via {}: {:?}", bootstrap_addr, e),
+    }
+}
+
+/// Queries `addr` for the peers it currently believes are alive and logs them.
+pub async fn list_peers(addr: &str, self_addr: &str) {
+    match query_peer(addr, Message::ListPeers, self_addr).await {
+        Ok(Message::Peers { peers }) => {
+            info!("{} live peer(s) known to {}:", peers.len(), addr);
+            for peer in &peers {
+                info!("  {} ({})", peer.addr, peer.id);
             }
         },
-        Err(e) => {
-            error!("Could not connect to bootstrap node: {:?}", e);
+        Ok(resp) => error!("Unexpected reply to ListPeers: {:?}", resp),
+        Err(e) => error!("Could not list peers via {}: {:?}", addr, e),
+    }
+}
+
+/// Locates the k nodes closest to `SHA-256(key)` via an iterative Kademlia
+/// lookup seeded at `bootstrap_addr`, then stores the value on each of them.
+pub async fn store_value(bootstrap_addr: &str, key: String, value: String, self_addr: &str) {
+    let target = dht::id_from_key(&key);
+    let closest = iterative_lookup(bootstrap_addr, target, self_addr).await;
+
+    if closest.is_empty() {
+        error!("No peers known; could not locate nodes responsible for {}", key);
+        return;
+    }
+
+    for peer in &closest {
+        let msg = Message::Store { key: key.clone(), value: value.clone() };
+        match query_peer(&peer.addr, msg, self_addr).await {
+            Ok(resp) => info!("Store on {} acknowledged: {:?}", peer.addr, resp),
+            Err(e) => warn!("Store on {} failed: {:?}", peer.addr, e),
         }
     }
 }
+
+/// Looks up `key` via an iterative Kademlia `FindValue` lookup seeded at
+/// `bootstrap_addr`.
+pub async fn lookup_value(bootstrap_addr: &str, key: String, self_addr: &str) -> Option<String> {
+    let (_, value) = iterative_find_value(bootstrap_addr, &key, self_addr).await;
+    match &value {
+        Some(v) => info!("Lookup success: {} -> {}", key, v),
+        None => info!("Lookup miss: {}", key),
+    }
+    value
+}
+
+/// Splits `path` into content-addressed chunks, keeps a local copy so this
+/// node can serve it, and publishes the manifest into the DHT keyed by its
+/// root hash. Returns the root hash (the file's `file_id`).
+pub async fn publish_file(bootstrap_addr: &str, path: &Path, self_addr: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let manifest = file_manager::build_manifest(path, file_manager::DEFAULT_CHUNK_SIZE)?;
+
+    std::fs::create_dir_all(SHARED_DIR)?;
+    std::fs::copy(path, shared_path(&manifest.root_hash))?;
+
+    let manifest_json = serde_json::to_string(&manifest)?;
+    // Keep a local copy so this node can answer FileRequests immediately,
+    // in addition to publishing it to the k closest nodes in the DHT.
+    crate::dht::GLOBAL_DHT.insert(manifest.root_hash.clone(), manifest_json.clone()).await;
+    store_value(bootstrap_addr, manifest.root_hash.clone(), manifest_json, self_addr).await;
+
+    info!("Published {} as {} ({} chunks)", path.display(), manifest.root_hash, manifest.chunks.len());
+    Ok(manifest.root_hash)
+}
+
+/// Fetches `file_id` from `source_addr` chunk by chunk, verifying each one
+/// against the manifest before writing it to `output`. Chunks already
+/// present and verified on disk are skipped, so an interrupted fetch can
+/// simply be re-run to resume.
+pub async fn fetch_file(source_addr: &str, file_id: &str, output: &Path, self_addr: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+    {
+        // Negotiate once up front so we fail fast if the peer can't serve
+        // chunked files, instead of discovering it one FileRequest at a time.
+        let (_, _, _, caps) = connect_and_negotiate(source_addr, self_addr).await?;
+        if !caps.iter().any(|c| c == protocol::CAP_CHUNKED_FILES) {
+            return Err(format!("{} does not advertise {} support", source_addr, protocol::CAP_CHUNKED_FILES).into());
+        }
+    }
+
+    let manifest = match query_peer(source_addr, Message::Lookup { key: file_id.to_string() }, self_addr).await? {
+        Message::Store { value, .. } => serde_json::from_str::<Manifest>(&value)?,
+        other => return Err(format!("unexpected reply to manifest Lookup: {:?}", other).into()),
+    };
+
+    // Create (or size) the output file up front so a zero-chunk manifest
+    // still produces the file it claims to, rather than silently leaving
+    // nothing on disk while reporting success.
+    file_manager::ensure_output_file(output, &manifest)?;
+
+    for chunk in &manifest.chunks {
+        if file_manager::has_valid_chunk(output, &manifest, chunk.index) {
+            info!("Chunk {} of {} already present and verified, skipping", chunk.index, file_id);
+            continue;
+        }
+
+        let req = Message::FileRequest { file_id: file_id.to_string(), chunk_index: chunk.index };
+        match query_peer(source_addr, req, self_addr).await? {
+            Message::FileData { chunk_index, data, .. } => {
+                if !file_manager::verify_chunk(&manifest, chunk_index, &data) {
+                    return Err(format!("chunk {} of {} failed hash verification", chunk_index, file_id).into());
+                }
+                file_manager::write_chunk(output, &manifest, chunk_index, &data)?;
+            },
+            other => return Err(format!("unexpected reply to FileRequest: {:?}", other).into()),
+        }
+    }
+
+    info!("Fetched {} ({} chunks) to {}", file_id, manifest.chunks.len(), output.display());
+    Ok(())
+}
@@ -2,23 +2,64 @@
     protocol.rs
     ----------------------------------------------------------------------------
     Defines the P2P messaging protocol using a strongly-typed enum.
-    
+
     Features:
       - Each variant represents a different type of message exchanged between nodes.
       - Uses Serde for JSON serialization/deserialization.
-    
+
     Developer Notes:
-      - The protocol supports Join, Ping/Pong, Store, Lookup, FileRequest, and FileData messages.
+      - The protocol supports Hello, Join, Ping/Pong, ListPeers/Peers, Store,
+        Lookup, FindNode, FindValue, FileRequest, and FileData messages.
       - Additional variants can be added as the project expands.
     ----------------------------------------------------------------------------
 */
 
 use serde::{Serialize, Deserialize};
 
+/// The protocol version this build speaks. Bump whenever a breaking change
+/// is made to the `Message` wire format.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The oldest peer protocol version this build still knows how to talk to.
+pub const MIN_SUPPORTED_PROTOCOL_VERSION: u32 = 1;
+
+/// Capability tags advertised in `Hello`. Gate feature-specific behavior
+/// (e.g. chunked file transfer) on the peer having advertised the matching
+/// capability rather than assuming it from the protocol version alone.
+pub const CAP_ENCRYPTION: &str = "encryption";
+pub const CAP_KADEMLIA: &str = "kademlia";
+pub const CAP_CHUNKED_FILES: &str = "chunked-files";
+
+/// The full set of capabilities this build supports.
+pub fn local_capabilities() -> Vec<String> {
+    vec![CAP_ENCRYPTION.to_string(), CAP_KADEMLIA.to_string(), CAP_CHUNKED_FILES.to_string()]
+}
+
+/// A peer's routable identity: its 256-bit Kademlia ID (hex-encoded) and the
+/// address it can be reached at.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PeerInfo {
+    pub id: String,
+    pub addr: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Message {
-    /// Join: Sent by a node to join the network.
-    Join { node_id: String },
+    /// Hello: Exchanged as the very first message on every connection (after
+    /// the encryption handshake) to negotiate protocol version and feature
+    /// capabilities before anything else is sent.
+    Hello { protocol_version: u32, capabilities: Vec<String>, node_id: String },
+
+    /// NegotiationError: Sent instead of a `Hello` reply when the peer's
+    /// advertised version is older than `MIN_SUPPORTED_PROTOCOL_VERSION`
+    /// (or its first message is not a `Hello`); the connection is closed
+    /// immediately afterwards. Capabilities never cause a rejection: the
+    /// negotiated set is whatever both sides advertised in common.
+    NegotiationError { reason: String },
+
+    /// Join: Sent by a node to join the network, advertising its own address
+    /// so the recipient can add it to its Kademlia routing table.
+    Join { node_id: String, addr: String },
 
     /// Ping: A heartbeat message to verify node availability.
     Ping,
@@ -26,6 +67,13 @@ pub enum Message {
     /// Pong: Response to a Ping message.
     Pong,
 
+    /// ListPeers: Requests the recipient's currently known live peers.
+    ListPeers,
+
+    /// Peers: Reply to `Join`/`ListPeers` gossiping a sample of peers the
+    /// recipient currently believes are alive.
+    Peers { peers: Vec<PeerInfo> },
+
     /// Store: Instructs the receiver to store a key-value pair in the DHT.
     /// The response might echo the stored pair.
     Store { key: String, value: String },
@@ -34,11 +82,30 @@ pub enum Message {
     /// The response should include the value if found.
     Lookup { key: String },
 
-    /// FileRequest: Requests a file or a file chunk.
-    FileRequest { file_id: String },
+    /// FindNode: Kademlia query for the k peers closest to `target`
+    /// (hex-encoded 256-bit ID). `requester` lets the recipient add the
+    /// asker to its own routing table.
+    FindNode { requester: PeerInfo, target: String },
+
+    /// FindValue: Kademlia query for a value by key. The recipient replies
+    /// with `ValueFound` if it holds the value locally, otherwise with the
+    /// k peers closest to `SHA-256(key)`.
+    FindValue { requester: PeerInfo, key: String },
+
+    /// NodesFound: Reply to `FindNode`/`FindValue` carrying the k closest
+    /// known peers.
+    NodesFound { peers: Vec<PeerInfo> },
+
+    /// ValueFound: Reply to `FindValue` when the recipient holds the value.
+    ValueFound { key: String, value: String },
+
+    /// FileRequest: Requests a single chunk of a file, identified by the
+    /// file's manifest root hash and the chunk's index within it.
+    FileRequest { file_id: String, chunk_index: usize },
 
-    /// FileData: Carries the data of a requested file or chunk.
-    FileData { file_id: String, data: Vec<u8> },
+    /// FileData: Carries one requested chunk's data, so chunks can be
+    /// fetched out of order and the transfer can resume after a restart.
+    FileData { file_id: String, chunk_index: usize, data: Vec<u8> },
 
     /// Ack: Acknowledgment message used as a default reply.
     Ack,